@@ -1,20 +1,24 @@
+use chrono::{DateTime, FixedOffset};
 use regex::Regex;
-use serde::Deserialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::io::{self, Write};
+use std::path::PathBuf;
 use std::process;
 use std::time::Duration;
 
-/// RSS represents the root XML structure of an RSS feed
+/// RssDoc represents the root XML structure of an RSS 0.91/2.0 feed
 #[derive(Deserialize, Debug)]
-struct Rss {
+struct RssDoc {
     #[serde(rename = "channel")]
-    channel: Channel,
+    channel: RssChannel,
 }
 
-/// Channel represents the main content container in an RSS feed
+/// RssChannel represents the `<channel>` element of an RSS 0.91/2.0 feed
 #[derive(Deserialize, Debug)]
-struct Channel {
+struct RssChannel {
     #[serde(rename = "title")]
     title: String,
     #[serde(rename = "description")]
@@ -22,19 +26,637 @@ struct Channel {
     #[serde(rename = "link")]
     link: String,
     #[serde(rename = "item")]
-    items: Vec<Item>,
+    items: Vec<RssItem>,
 }
 
-/// Item represents a single article in the RSS feed
+/// RssItem represents a single `<item>` in an RSS 0.91/2.0 feed
 #[derive(Deserialize, Debug)]
-struct Item {
+struct RssItem {
     #[serde(rename = "title")]
     title: String,
     #[serde(rename = "link")]
     link: String,
-    #[serde(rename = "description")]
+    #[serde(rename = "description", default)]
+    description: String,
+    #[serde(rename = "pubDate", default)]
+    pub_date: String,
+}
+
+/// RdfDoc represents the root of an RSS 1.0 / RDF feed, where `<item>`
+/// elements are siblings of `<channel>` rather than nested inside it
+#[derive(Deserialize, Debug)]
+struct RdfDoc {
+    #[serde(rename = "channel")]
+    channel: RdfChannel,
+    #[serde(rename = "item", default)]
+    items: Vec<RssItem>,
+}
+
+/// RdfChannel represents the `<channel>` element of an RSS 1.0 / RDF feed
+#[derive(Deserialize, Debug)]
+struct RdfChannel {
+    #[serde(rename = "title")]
+    title: String,
+    #[serde(rename = "description", default)]
+    description: String,
+    #[serde(rename = "link")]
+    link: String,
+}
+
+/// AtomDoc represents the root `<feed>` element of an Atom 1.0 feed
+#[derive(Deserialize, Debug)]
+struct AtomDoc {
+    #[serde(rename = "title")]
+    title: String,
+    #[serde(rename = "subtitle", default)]
+    subtitle: String,
+    #[serde(rename = "link", default)]
+    links: Vec<AtomLink>,
+    #[serde(rename = "entry", default)]
+    entries: Vec<AtomEntry>,
+}
+
+/// AtomLink represents an Atom `<link>` element, whose URL is carried in
+/// the `href` attribute rather than in element text
+#[derive(Deserialize, Debug)]
+struct AtomLink {
+    #[serde(rename = "href", default)]
+    href: String,
+    #[serde(rename = "rel", default)]
+    rel: String,
+}
+
+/// AtomEntry represents a single Atom `<entry>`
+#[derive(Deserialize, Debug)]
+struct AtomEntry {
+    #[serde(rename = "title")]
+    title: String,
+    #[serde(rename = "summary", default)]
+    summary: String,
+    #[serde(rename = "content", default)]
+    content: String,
+    #[serde(rename = "link", default)]
+    links: Vec<AtomLink>,
+    #[serde(rename = "updated", default)]
+    updated: String,
+}
+
+/// FeedVersion identifies which syndication format a downloaded document
+/// uses, so it can be parsed into the common `Channel`/`Item` model
+#[derive(Debug, PartialEq, Eq)]
+enum FeedVersion {
+    /// RSS 0.91 / 2.0: `<rss><channel><item>`
+    Rss,
+    /// RSS 1.0 / RDF: `<rdf:RDF><channel>` with `<item>` as siblings of `<channel>`
+    Rdf,
+    /// Atom 1.0: `<feed><entry>`
+    Atom,
+}
+
+/// Returns the tag name of the document's root element, skipping the XML
+/// prolog, comments and DOCTYPE, so callers don't have to scan the whole
+/// body (where extension elements like `<feedburner:origLink>` could
+/// otherwise be mistaken for the root)
+fn root_element_name(content: &str) -> Option<&str> {
+    let mut rest = content.trim_start();
+    loop {
+        if let Some(body) = rest.strip_prefix("<?") {
+            rest = body[body.find("?>")? + 2..].trim_start();
+        } else if let Some(body) = rest.strip_prefix("<!--") {
+            rest = body[body.find("-->")? + 3..].trim_start();
+        } else if let Some(body) = rest.strip_prefix("<!") {
+            rest = body[body.find('>')? + 1..].trim_start();
+        } else {
+            let tag = rest.strip_prefix('<')?;
+            let end = tag.find(|c: char| c.is_whitespace() || c == '>' || c == '/')?;
+            return Some(&tag[..end]);
+        }
+    }
+}
+
+/// Detects the feed format by looking at the root element's tag name,
+/// not by scanning the whole document for a substring
+fn detect_feed_version(content: &str) -> FeedVersion {
+    match root_element_name(content) {
+        Some("feed") => FeedVersion::Atom,
+        Some(tag) if tag.eq_ignore_ascii_case("rdf:RDF") || tag == "rdf" => FeedVersion::Rdf,
+        _ => FeedVersion::Rss,
+    }
+}
+
+/// Picks the href an Atom link points to, preferring `rel="alternate"`
+/// (or an unmarked link, which defaults to alternate) over other
+/// relations such as `self`
+fn atom_link_href(links: &[AtomLink]) -> String {
+    links
+        .iter()
+        .find(|l| l.rel.is_empty() || l.rel == "alternate")
+        .or_else(|| links.first())
+        .map(|l| l.href.clone())
+        .unwrap_or_default()
+}
+
+impl From<RssItem> for Item {
+    fn from(item: RssItem) -> Self {
+        Item {
+            title: item.title,
+            link: item.link,
+            description: item.description,
+            pub_date: item.pub_date,
+        }
+    }
+}
+
+impl From<AtomEntry> for Item {
+    fn from(entry: AtomEntry) -> Self {
+        Item {
+            title: entry.title,
+            link: atom_link_href(&entry.links),
+            description: if entry.summary.is_empty() {
+                entry.content
+            } else {
+                entry.summary
+            },
+            pub_date: entry.updated,
+        }
+    }
+}
+
+/// Parses a downloaded feed document into the common `Channel`
+/// representation, detecting RSS 0.91/2.0, RSS 1.0/RDF and Atom 1.0
+fn parse_feed(content: &str) -> Result<Channel, Box<dyn std::error::Error>> {
+    match detect_feed_version(content) {
+        FeedVersion::Rss => {
+            let doc: RssDoc = serde_xml_rs::from_str(content)?;
+            Ok(Channel {
+                title: doc.channel.title,
+                description: doc.channel.description,
+                link: doc.channel.link,
+                items: doc.channel.items.into_iter().map(Item::from).collect(),
+            })
+        }
+        FeedVersion::Rdf => {
+            let doc: RdfDoc = serde_xml_rs::from_str(content)?;
+            Ok(Channel {
+                title: doc.channel.title,
+                description: doc.channel.description,
+                link: doc.channel.link,
+                items: doc.items.into_iter().map(Item::from).collect(),
+            })
+        }
+        FeedVersion::Atom => {
+            let doc: AtomDoc = serde_xml_rs::from_str(content)?;
+            Ok(Channel {
+                title: doc.title,
+                description: doc.subtitle,
+                link: atom_link_href(&doc.links),
+                items: doc.entries.into_iter().map(Item::from).collect(),
+            })
+        }
+    }
+}
+
+/// CacheEntry is the on-disk record kept for a feed URL so subsequent
+/// fetches can send a conditional GET instead of re-downloading the body
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+/// Returns the path a feed URL's cache entry would be stored at, inside
+/// a platform cache directory (falling back to the system temp dir)
+fn cache_file_path(url: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("adncrab")
+        .join(format!("{:x}.json", hasher.finish()))
+}
+
+/// Loads a feed's cache entry from disk, if one exists
+fn load_cache_entry(url: &str) -> Option<CacheEntry> {
+    let data = std::fs::read_to_string(cache_file_path(url)).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+/// Writes a feed's cache entry to disk, creating the cache directory if needed
+fn save_cache_entry(url: &str, entry: &CacheEntry) -> Result<(), Box<dyn std::error::Error>> {
+    let path = cache_file_path(url);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// SeenStore records, per category, the link of every article already
+/// shown to the user so repeated launches can tell new articles from
+/// ones already read
+type SeenStore = HashMap<u32, HashSet<String>>;
+
+/// Returns the on-disk path of the read/unread state file
+fn seen_store_path() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("adncrab")
+        .join("seen.json")
+}
+
+/// Loads the read/unread state, treating a missing or unreadable file as
+/// "nothing has been seen yet"
+fn load_seen_store() -> SeenStore {
+    std::fs::read_to_string(seen_store_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Persists the read/unread state to disk, creating its directory if needed
+fn save_seen_store(store: &SeenStore) -> Result<(), Box<dyn std::error::Error>> {
+    let path = seen_store_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(store)?)?;
+    Ok(())
+}
+
+/// Filters `items` down to the ones a "new articles" listing should show:
+/// every item when `only_new` is false, or just the ones not already in
+/// `seen` when `only_new` is true
+fn filter_items_by_seen(items: &[Item], seen: &HashSet<String>, only_new: bool) -> Vec<Item> {
+    items
+        .iter()
+        .filter(|item| !only_new || !seen.contains(&item.link))
+        .cloned()
+        .collect()
+}
+
+/// Downloads `url` with `client` and parses it into the common `Channel`
+/// representation; factored out of `RssReader::fetch_rss_feed` so it can
+/// also be spawned as an independent, `'static` task when aggregating
+/// several feeds concurrently.
+///
+/// Unless `no_cache` is set, this sends a conditional GET using any
+/// cached `ETag`/`Last-Modified` for `url` and, on a `304 Not Modified`
+/// response, parses the cached body instead of re-reading the network.
+async fn fetch_and_parse(
+    client: &reqwest::Client,
+    url: &str,
+    no_cache: bool,
+) -> Result<Channel, Box<dyn std::error::Error>> {
+    let cached = if no_cache { None } else { load_cache_entry(url) };
+
+    let mut request = client.get(url).timeout(Duration::from_secs(15));
+    if let Some(entry) = &cached {
+        if let Some(etag) = &entry.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = request.send().await?;
+
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        let entry = cached.ok_or("Server returned 304 Not Modified but no cache entry was found")?;
+        return parse_feed(&entry.body);
+    }
+
+    if !response.status().is_success() {
+        return Err(format!("Unexpected status code: {}", response.status()).into());
+    }
+
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+    let last_modified = response
+        .headers()
+        .get(reqwest::header::LAST_MODIFIED)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+
+    let content = response.text().await?;
+
+    if !no_cache {
+        let entry = CacheEntry {
+            etag,
+            last_modified,
+            body: content.clone(),
+        };
+        if let Err(e) = save_cache_entry(url, &entry) {
+            eprintln!("Warning: failed to write feed cache for {}: {}", url, e);
+        }
+    }
+
+    parse_feed(&content)
+}
+
+/// Parses an `Item`'s `pub_date` into a comparable timestamp. RSS/RDF
+/// items carry RFC 822 / RFC 2822 dates (e.g. `Wed, 02 Oct 2024
+/// 13:00:00 +0200`), while Atom's `<updated>` is RFC 3339 /
+/// ISO 8601 (e.g. `2024-10-02T13:00:00+02:00`); both are stored in the
+/// same field, so both grammars are tried here. Returns `None` when
+/// neither matches.
+fn parse_pub_date(pub_date: &str) -> Option<DateTime<FixedOffset>> {
+    let trimmed = pub_date.trim();
+    DateTime::parse_from_rfc2822(trimmed)
+        .or_else(|_| DateTime::parse_from_rfc3339(trimmed))
+        .ok()
+}
+
+/// Converts an `Item`'s `pub_date` (RFC 822 or RFC 3339, see
+/// `parse_pub_date`) into an RFC 3339 timestamp suitable for JSON Feed's
+/// `date_published`, returning `None` when the date does not parse
+fn pub_date_to_rfc3339(pub_date: &str) -> Option<String> {
+    parse_pub_date(pub_date).map(|date| date.to_rfc3339())
+}
+
+/// OutputFormat selects between the human-readable terminal output and
+/// the two machine-readable formats a `--format` flag can request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Human,
+    JsonFeed,
+    Ndjson,
+}
+
+/// JsonFeedItem is a single entry in JSON Feed 1.1 output, and also the
+/// shape used for each line of NDJSON output
+#[derive(Serialize, Debug)]
+struct JsonFeedItem {
+    id: String,
+    url: String,
+    title: String,
+    content_text: String,
+    date_published: Option<String>,
+}
+
+/// JsonFeedDoc is the JSON Feed 1.1 document `--format json-feed` emits
+#[derive(Serialize, Debug)]
+struct JsonFeedDoc {
+    version: String,
+    title: String,
+    items: Vec<JsonFeedItem>,
+}
+
+/// QueryField names an `Item` field the query grammar can compare against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QueryField {
+    Title,
+    Description,
+    Link,
+    PubDate,
+}
+
+/// QueryOp is a single comparison in a query expression
+#[derive(Debug)]
+enum QueryOp {
+    RegexMatch(Regex),
+    Eq(String),
+    Ne(String),
+    Contains(String),
+}
+
+/// Predicate is the parsed AST of a query expression, combining field
+/// comparisons with `and`/`or`/`not` and parentheses
+#[derive(Debug)]
+enum Predicate {
+    Compare(QueryField, QueryOp),
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+    Not(Box<Predicate>),
+}
+
+impl Predicate {
+    /// Evaluates this predicate against a single item
+    fn eval(&self, item: &Item) -> bool {
+        match self {
+            Predicate::Compare(field, op) => {
+                let value = match field {
+                    QueryField::Title => &item.title,
+                    QueryField::Description => &item.description,
+                    QueryField::Link => &item.link,
+                    QueryField::PubDate => &item.pub_date,
+                };
+                match op {
+                    QueryOp::RegexMatch(re) => re.is_match(value),
+                    QueryOp::Eq(expected) => value == expected,
+                    QueryOp::Ne(expected) => value != expected,
+                    QueryOp::Contains(needle) => value.contains(needle.as_str()),
+                }
+            }
+            Predicate::And(a, b) => a.eval(item) && b.eval(item),
+            Predicate::Or(a, b) => a.eval(item) || b.eval(item),
+            Predicate::Not(p) => !p.eval(item),
+        }
+    }
+}
+
+/// Token is a lexical unit of a query expression
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Op(String),
+    Str(String),
+    LParen,
+    RParen,
+}
+
+/// Splits a query expression into tokens: identifiers/keywords
+/// (`title`, `and`, `not`, ...), operators (`=~`, `==`, `!=`), quoted
+/// string literals and parentheses
+fn tokenize(input: &str) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::LParen);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::RParen);
+        } else if c == '"' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(ch) => value.push(ch),
+                    None => return Err("Unterminated string literal in query".into()),
+                }
+            }
+            tokens.push(Token::Str(value));
+        } else if c == '=' || c == '!' {
+            let mut op = String::new();
+            op.push(chars.next().unwrap());
+            if chars.peek().is_some_and(|&next| next == '~' || next == '=') {
+                op.push(chars.next().unwrap());
+            }
+            tokens.push(Token::Op(op));
+        } else {
+            let mut ident = String::new();
+            while let Some(&ch) = chars.peek() {
+                if ch.is_whitespace() || ch == '(' || ch == ')' {
+                    break;
+                }
+                ident.push(ch);
+                chars.next();
+            }
+            tokens.push(Token::Ident(ident));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// QueryParser turns a token stream into a `Predicate` AST using
+/// recursive descent, with `not` binding tighter than `and`, which
+/// binds tighter than `or`
+struct QueryParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl QueryParser {
+    fn new(tokens: Vec<Token>) -> Self {
+        QueryParser { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_expr(&mut self) -> Result<Predicate, Box<dyn std::error::Error>> {
+        let mut left = self.parse_and()?;
+        while let Some(Token::Ident(keyword)) = self.peek() {
+            if keyword == "or" {
+                self.advance();
+                let right = self.parse_and()?;
+                left = Predicate::Or(Box::new(left), Box::new(right));
+            } else {
+                break;
+            }
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Predicate, Box<dyn std::error::Error>> {
+        let mut left = self.parse_not()?;
+        while let Some(Token::Ident(keyword)) = self.peek() {
+            if keyword == "and" {
+                self.advance();
+                let right = self.parse_not()?;
+                left = Predicate::And(Box::new(left), Box::new(right));
+            } else {
+                break;
+            }
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Predicate, Box<dyn std::error::Error>> {
+        if matches!(self.peek(), Some(Token::Ident(keyword)) if keyword == "not") {
+            self.advance();
+            let inner = self.parse_not()?;
+            return Ok(Predicate::Not(Box::new(inner)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<Predicate, Box<dyn std::error::Error>> {
+        match self.advance() {
+            Some(Token::LParen) => {
+                let inner = self.parse_expr()?;
+                match self.advance() {
+                    Some(Token::RParen) => Ok(inner),
+                    other => Err(format!("Expected ')' in query, found {:?}", other).into()),
+                }
+            }
+            Some(Token::Ident(field_name)) => {
+                let field = match field_name.as_str() {
+                    "title" => QueryField::Title,
+                    "description" => QueryField::Description,
+                    "link" => QueryField::Link,
+                    "pubdate" => QueryField::PubDate,
+                    other => return Err(format!("Unknown field '{}' in query", other).into()),
+                };
+
+                let op = match self.advance() {
+                    Some(Token::Op(op)) if op == "=~" => {
+                        QueryOp::RegexMatch(Regex::new(&self.expect_str()?)?)
+                    }
+                    Some(Token::Op(op)) if op == "==" => QueryOp::Eq(self.expect_str()?),
+                    Some(Token::Op(op)) if op == "!=" => QueryOp::Ne(self.expect_str()?),
+                    Some(Token::Ident(keyword)) if keyword == "contains" => {
+                        QueryOp::Contains(self.expect_str()?)
+                    }
+                    other => {
+                        return Err(format!("Expected a comparison operator, found {:?}", other).into())
+                    }
+                };
+
+                Ok(Predicate::Compare(field, op))
+            }
+            other => Err(format!("Unexpected token in query: {:?}", other).into()),
+        }
+    }
+
+    fn expect_str(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        match self.advance() {
+            Some(Token::Str(value)) => Ok(value),
+            other => Err(format!("Expected a quoted string, found {:?}", other).into()),
+        }
+    }
+}
+
+/// Parses a query expression such as
+/// `title =~ "(?i)elezioni" and not description contains "sondaggio"`
+/// into a `Predicate` that can be evaluated against items
+fn parse_query(expr: &str) -> Result<Predicate, Box<dyn std::error::Error>> {
+    let tokens = tokenize(expr)?;
+    let mut parser = QueryParser::new(tokens);
+    let predicate = parser.parse_expr()?;
+    if parser.pos != parser.tokens.len() {
+        return Err("Unexpected trailing tokens in query".into());
+    }
+    Ok(predicate)
+}
+
+/// Channel is the common, format-independent representation of a feed
+/// that `display_feed` and the rest of the reader operate on
+#[derive(Debug)]
+struct Channel {
+    title: String,
+    description: String,
+    link: String,
+    items: Vec<Item>,
+}
+
+/// Item is the common, format-independent representation of a single
+/// article, regardless of whether it came from an RSS `<item>` or an
+/// Atom `<entry>`
+#[derive(Debug, Clone, PartialEq)]
+struct Item {
+    title: String,
+    link: String,
     description: String,
-    #[serde(rename = "pubDate")]
     pub_date: String,
 }
 
@@ -43,11 +665,15 @@ struct RssReader {
     category_urls: HashMap<u32, &'static str>,
     html_tag_regex: Regex,
     client: reqwest::Client,
+    no_cache: bool,
+    format: OutputFormat,
 }
 
 impl RssReader {
-    /// Creates a new RssReader instance
-    fn new() -> Result<Self, Box<dyn std::error::Error>> {
+    /// Creates a new RssReader instance. When `no_cache` is set, feed
+    /// fetches bypass the on-disk ETag/Last-Modified cache entirely.
+    /// `format` selects between human-readable and machine-readable output.
+    fn new(no_cache: bool, format: OutputFormat) -> Result<Self, Box<dyn std::error::Error>> {
         let mut category_urls = HashMap::new();
         category_urls.insert(1, "https://www.adnkronos.com/RSS_PrimaPagina.xml");
         category_urls.insert(2, "https://www.adnkronos.com/RSS_Ultimora.xml");
@@ -68,6 +694,8 @@ impl RssReader {
             category_urls,
             html_tag_regex,
             client,
+            no_cache,
+            format,
         })
     }
 
@@ -80,23 +708,62 @@ impl RssReader {
         clean_text.replace("&nbsp;", " ")
     }
 
-    /// Fetches and parses the RSS feed from the given URL
-    async fn fetch_rss_feed(&self, url: &str) -> Result<Rss, Box<dyn std::error::Error>> {
-        let response = self
-            .client
-            .get(url)
-            .timeout(Duration::from_secs(15))
-            .send()
-            .await?;
+    /// Fetches and parses the feed from the given URL, auto-detecting
+    /// whether it is RSS 0.91/2.0, RSS 1.0/RDF or Atom 1.0
+    async fn fetch_rss_feed(&self, url: &str) -> Result<Channel, Box<dyn std::error::Error>> {
+        fetch_and_parse(&self.client, url, self.no_cache).await
+    }
+
+    /// Concurrently fetches every configured category, merges the
+    /// resulting items into a single feed (de-duplicated by link and
+    /// sorted by publication date, most recent first), and skips any
+    /// feed that fails to download or parse with a warning
+    async fn fetch_all_categories(&self) -> Channel {
+        let mut category_ids: Vec<u32> = self.category_urls.keys().copied().collect();
+        category_ids.sort_unstable();
 
-        if !response.status().is_success() {
-            return Err(format!("Unexpected status code: {}", response.status()).into());
+        let mut set = tokio::task::JoinSet::new();
+        for (index, &category) in category_ids.iter().enumerate() {
+            let client = self.client.clone();
+            let url = self.category_urls[&category].to_string();
+            let no_cache = self.no_cache;
+            set.spawn(async move {
+                let result = fetch_and_parse(&client, &url, no_cache)
+                    .await
+                    .map_err(|e| e.to_string());
+                (index, url, result)
+            });
         }
 
-        let content = response.text().await?;
-        let rss: Rss = serde_xml_rs::from_str(&content)?;
+        // Results are slotted back in by `index` (assigned from the sorted
+        // category order above) rather than appended in completion order,
+        // so the merge below has a deterministic "original order" to fall
+        // back to when a `pub_date` fails to parse.
+        let mut channels: Vec<Option<Channel>> = (0..category_ids.len()).map(|_| None).collect();
+        while let Some(outcome) = set.join_next().await {
+            match outcome {
+                Ok((index, _, Ok(channel))) => channels[index] = Some(channel),
+                Ok((_, url, Err(e))) => eprintln!("Warning: skipping {}: {}", url, e),
+                Err(e) => eprintln!("Warning: fetch task failed: {}", e),
+            }
+        }
+
+        let mut items: Vec<Item> = channels.into_iter().flatten().flat_map(|c| c.items).collect();
+
+        let mut seen_links = HashSet::new();
+        items.retain(|item| seen_links.insert(item.link.clone()));
+
+        // Items whose `pub_date` does not parse sort after all dated items
+        // (under `Reverse`, `None` orders after every `Some`) and, as the sort
+        // is stable, keep their original order among themselves.
+        items.sort_by_cached_key(|item| std::cmp::Reverse(parse_pub_date(&item.pub_date)));
 
-        Ok(rss)
+        Channel {
+            title: "Tutte le categorie".to_string(),
+            description: "Merged feed across all Adnkronos categories".to_string(),
+            link: String::new(),
+            items,
+        }
     }
 
     /// Displays the available RSS categories
@@ -111,10 +778,40 @@ impl RssReader {
         println!("6: Economia");
         println!("7: Finanza");
         println!("8: Sport");
+        println!("9: Tutte le categorie");
+        println!("10: Query feeds (filter expression)");
+        println!("11: Solo articoli nuovi (per categoria)");
+        println!("12: Segna tutto come letto (per categoria)");
         print!("\nSelect category number: ");
         io::stdout().flush().unwrap();
     }
 
+    /// Aggregates every category and displays only the items matching
+    /// the given query expression (see `parse_query` for the grammar)
+    async fn run_query(&self, expr: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let predicate = parse_query(expr)?;
+        let channel = self.fetch_all_categories().await;
+
+        let matches: Vec<Item> = channel
+            .items
+            .into_iter()
+            .map(|item| Item {
+                description: self.remove_tags(&item.description),
+                ..item
+            })
+            .filter(|item| predicate.eval(item))
+            .collect();
+
+        let result = Channel {
+            title: format!("Query: {}", expr),
+            description: format!("{} matching articles", matches.len()),
+            link: String::new(),
+            items: matches,
+        };
+
+        self.output_channel(&result)
+    }
+
     /// Gets user input for category selection
     fn get_category_input(&self) -> Result<u32, Box<dyn std::error::Error>> {
         let mut input = String::new();
@@ -124,12 +821,12 @@ impl RssReader {
     }
 
     /// Displays RSS feed content
-    fn display_feed(&self, rss: &Rss) {
-        println!("\nTitle: {}", rss.channel.title);
-        println!("Link: {}", rss.channel.link);
-        println!("Description: {}\n", rss.channel.description);
+    fn display_feed(&self, channel: &Channel) {
+        println!("\nTitle: {}", channel.title);
+        println!("Link: {}", channel.link);
+        println!("Description: {}\n", channel.description);
 
-        for item in &rss.channel.items {
+        for item in &channel.items {
             println!("Title: {}", item.title);
             println!("Link: {}", item.link);
             println!("Description: {}", self.remove_tags(&item.description));
@@ -140,6 +837,121 @@ impl RssReader {
         }
     }
 
+    /// Converts a `Channel`'s items into JSON Feed items, converting
+    /// `pub_date` to RFC 3339 and cleaning descriptions with `remove_tags`
+    fn channel_to_feed_items(&self, channel: &Channel) -> Vec<JsonFeedItem> {
+        channel
+            .items
+            .iter()
+            .map(|item| JsonFeedItem {
+                id: item.link.clone(),
+                url: item.link.clone(),
+                title: item.title.clone(),
+                content_text: self.remove_tags(&item.description),
+                date_published: pub_date_to_rfc3339(&item.pub_date),
+            })
+            .collect()
+    }
+
+    /// Prints a channel as a single JSON Feed 1.1 document
+    fn print_json_feed(&self, channel: &Channel) -> Result<(), Box<dyn std::error::Error>> {
+        let doc = JsonFeedDoc {
+            version: "https://jsonfeed.org/version/1.1".to_string(),
+            title: channel.title.clone(),
+            items: self.channel_to_feed_items(channel),
+        };
+        println!("{}", serde_json::to_string(&doc)?);
+        Ok(())
+    }
+
+    /// Prints a channel as newline-delimited JSON, one item per line
+    fn print_ndjson(&self, channel: &Channel) -> Result<(), Box<dyn std::error::Error>> {
+        for item in self.channel_to_feed_items(channel) {
+            println!("{}", serde_json::to_string(&item)?);
+        }
+        Ok(())
+    }
+
+    /// Outputs a channel according to `self.format`: human-readable
+    /// terminal output, a JSON Feed document, or NDJSON
+    fn output_channel(&self, channel: &Channel) -> Result<(), Box<dyn std::error::Error>> {
+        match self.format {
+            OutputFormat::Human => {
+                self.display_feed(channel);
+                Ok(())
+            }
+            OutputFormat::JsonFeed => self.print_json_feed(channel),
+            OutputFormat::Ndjson => self.print_ndjson(channel),
+        }
+    }
+
+    /// Displays a category's feed tagging each article `[NEW]` or
+    /// `[read]` against the persisted seen-article store, optionally
+    /// hiding already-read articles, then records every shown link as
+    /// seen for that category. Honors `self.format`: under
+    /// `OutputFormat::Human` this prints the `[NEW]`/`[read]`-tagged
+    /// listing directly, otherwise the shown articles are handed to
+    /// `output_channel` so `--format json-feed`/`ndjson` works the same
+    /// way for the "only new" view as it does everywhere else
+    fn display_feed_with_status(
+        &self,
+        channel: &Channel,
+        category: u32,
+        only_new: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut store = load_seen_store();
+        let seen = store.entry(category).or_default();
+
+        let shown_items = filter_items_by_seen(&channel.items, seen, only_new);
+
+        if self.format == OutputFormat::Human {
+            println!("\nTitle: {}", channel.title);
+            println!("Link: {}", channel.link);
+            println!("Description: {}\n", channel.description);
+
+            for item in &shown_items {
+                let is_new = !seen.contains(&item.link);
+                println!("[{}] {}", if is_new { "NEW" } else { "read" }, item.title);
+                println!("Link: {}", item.link);
+                println!("Description: {}", self.remove_tags(&item.description));
+                println!("Published: {}\n", item.pub_date);
+                println!(
+                    "--------------------------------------------------------------------------------"
+                );
+            }
+        }
+
+        seen.extend(shown_items.iter().map(|item| item.link.clone()));
+
+        if let Err(e) = save_seen_store(&store) {
+            eprintln!("Warning: failed to persist read state: {}", e);
+        }
+
+        if self.format == OutputFormat::Human {
+            Ok(())
+        } else {
+            self.output_channel(&Channel {
+                title: channel.title.clone(),
+                description: channel.description.clone(),
+                link: channel.link.clone(),
+                items: shown_items,
+            })
+        }
+    }
+
+    /// Marks every item in `items` as read for `category` without
+    /// displaying them
+    fn mark_all_read(&self, category: u32, items: &[Item]) {
+        let mut store = load_seen_store();
+        let seen = store.entry(category).or_default();
+        seen.extend(items.iter().map(|item| item.link.clone()));
+
+        match save_seen_store(&store) {
+            Ok(()) => println!("Marked {} articles as read for category {}.", items.len(), category),
+            Err(e) => eprintln!("Warning: failed to persist read state: {}", e),
+        }
+    }
+
     /// Runs the RSS reader application
     async fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
         self.print_menu();
@@ -150,21 +962,77 @@ impl RssReader {
             process::exit(0);
         }
 
+        if category == 9 {
+            let channel = self.fetch_all_categories().await;
+            return self.output_channel(&channel);
+        }
+
+        if category == 10 {
+            print!("Enter query expression: ");
+            io::stdout().flush()?;
+            let mut expr = String::new();
+            io::stdin().read_line(&mut expr)?;
+            self.run_query(expr.trim()).await?;
+            return Ok(());
+        }
+
+        if category == 11 || category == 12 {
+            print!("Category number: ");
+            io::stdout().flush()?;
+            let sub_category = self.get_category_input()?;
+            let url = self
+                .category_urls
+                .get(&sub_category)
+                .ok_or("Invalid category number")?;
+            let channel = self.fetch_rss_feed(url).await?;
+
+            if category == 11 {
+                self.display_feed_with_status(&channel, sub_category, true)?;
+            } else {
+                self.mark_all_read(sub_category, &channel.items);
+            }
+
+            return Ok(());
+        }
+
         let url = self
             .category_urls
             .get(&category)
             .ok_or("Invalid category number")?;
 
-        let rss = self.fetch_rss_feed(url).await?;
-        self.display_feed(&rss);
+        let channel = self.fetch_rss_feed(url).await?;
+        self.display_feed_with_status(&channel, category, false)
+    }
+}
 
-        Ok(())
+/// Returns true if `--no-cache` was passed on the command line, bypassing
+/// the on-disk ETag/Last-Modified feed cache
+fn no_cache_flag() -> bool {
+    std::env::args().skip(1).any(|arg| arg == "--no-cache")
+}
+
+/// Parses an optional `--format <json-feed|ndjson>` command-line flag,
+/// defaulting to human-readable output when it is absent
+fn format_flag() -> Result<OutputFormat, Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "--format" {
+            let value = args.get(i + 1).ok_or("--format requires a value")?;
+            return match value.as_str() {
+                "json-feed" => Ok(OutputFormat::JsonFeed),
+                "ndjson" => Ok(OutputFormat::Ndjson),
+                other => Err(format!("Unknown output format '{}'", other).into()),
+            };
+        }
     }
+
+    Ok(OutputFormat::Human)
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let reader = RssReader::new()?;
+    let reader = RssReader::new(no_cache_flag(), format_flag()?)?;
 
     if let Err(e) = reader.run().await {
         eprintln!("Error: {}", e);
@@ -181,7 +1049,7 @@ mod tests {
 
     #[test]
     fn test_remove_tags() {
-        let reader = RssReader::new().unwrap();
+        let reader = RssReader::new(false, OutputFormat::Human).unwrap();
         let html = "<p>Hello <b>world</b>!</p>&nbsp;Test";
         let cleaned = reader.remove_tags(html);
         assert_eq!(cleaned, "Hello world! Test");
@@ -189,9 +1057,231 @@ mod tests {
 
     #[test]
     fn test_category_urls() {
-        let reader = RssReader::new().unwrap();
+        let reader = RssReader::new(false, OutputFormat::Human).unwrap();
         assert!(reader.category_urls.contains_key(&1));
         assert!(reader.category_urls.contains_key(&3));
         assert!(!reader.category_urls.contains_key(&10));
     }
+
+    #[test]
+    fn test_detect_feed_version_rss() {
+        let xml = "<?xml version=\"1.0\"?><rss version=\"2.0\"><channel></channel></rss>";
+        assert_eq!(detect_feed_version(xml), FeedVersion::Rss);
+    }
+
+    #[test]
+    fn test_detect_feed_version_rdf() {
+        let xml = "<?xml version=\"1.0\"?><rdf:RDF><channel></channel></rdf:RDF>";
+        assert_eq!(detect_feed_version(xml), FeedVersion::Rdf);
+    }
+
+    #[test]
+    fn test_detect_feed_version_atom() {
+        let xml = "<?xml version=\"1.0\"?><feed xmlns=\"http://www.w3.org/2005/Atom\"></feed>";
+        assert_eq!(detect_feed_version(xml), FeedVersion::Atom);
+    }
+
+    #[test]
+    fn test_detect_feed_version_rss_with_feedburner_extension() {
+        // A `<feedburner:origLink>` child contains the substring "<feed", but
+        // the root element is still `<rss>`.
+        let xml = r#"<?xml version="1.0"?>
+        <rss version="2.0">
+          <channel>
+            <item>
+              <title>Item</title>
+              <link>http://example.com/1</link>
+              <feedburner:origLink>http://example.com/1</feedburner:origLink>
+            </item>
+          </channel>
+        </rss>"#;
+        assert_eq!(detect_feed_version(xml), FeedVersion::Rss);
+    }
+
+    #[test]
+    fn test_parse_feed_rss() {
+        let xml = r#"<?xml version="1.0"?>
+        <rss version="2.0">
+          <channel>
+            <title>Test</title>
+            <description>Desc</description>
+            <link>http://example.com</link>
+            <item>
+              <title>Item 1</title>
+              <link>http://example.com/1</link>
+              <description>Body</description>
+              <pubDate>Wed, 02 Oct 2024 13:00:00 +0200</pubDate>
+            </item>
+          </channel>
+        </rss>"#;
+        let channel = parse_feed(xml).unwrap();
+        assert_eq!(channel.title, "Test");
+        assert_eq!(channel.items.len(), 1);
+        assert_eq!(channel.items[0].link, "http://example.com/1");
+    }
+
+    #[test]
+    fn test_parse_pub_date_valid() {
+        let date = parse_pub_date("Wed, 02 Oct 2024 13:00:00 +0200");
+        assert!(date.is_some());
+    }
+
+    #[test]
+    fn test_parse_pub_date_invalid() {
+        assert!(parse_pub_date("not a date").is_none());
+    }
+
+    #[test]
+    fn test_parse_pub_date_accepts_atom_rfc3339() {
+        let date = parse_pub_date("2024-10-02T13:00:00+02:00");
+        assert!(date.is_some());
+    }
+
+    #[test]
+    fn test_pub_date_to_rfc3339_from_rfc822() {
+        let rfc3339 = pub_date_to_rfc3339("Wed, 02 Oct 2024 13:00:00 +0200").unwrap();
+        assert_eq!(rfc3339, "2024-10-02T13:00:00+02:00");
+    }
+
+    #[test]
+    fn test_pub_date_to_rfc3339_from_atom_updated() {
+        let rfc3339 = pub_date_to_rfc3339("2024-10-02T13:00:00+02:00").unwrap();
+        assert_eq!(rfc3339, "2024-10-02T13:00:00+02:00");
+    }
+
+    #[test]
+    fn test_channel_to_feed_items_cleans_description() {
+        let reader = RssReader::new(false, OutputFormat::Human).unwrap();
+        let channel = Channel {
+            title: "Test".to_string(),
+            description: String::new(),
+            link: String::new(),
+            items: vec![Item {
+                title: "Item".to_string(),
+                link: "http://example.com/1".to_string(),
+                description: "<p>Body</p>".to_string(),
+                pub_date: "Wed, 02 Oct 2024 13:00:00 +0200".to_string(),
+            }],
+        };
+
+        let items = reader.channel_to_feed_items(&channel);
+        assert_eq!(items[0].content_text, "Body");
+        assert_eq!(items[0].date_published.as_deref(), Some("2024-10-02T13:00:00+02:00"));
+    }
+
+    #[test]
+    fn test_cache_file_path_is_deterministic() {
+        let url = "https://www.adnkronos.com/RSS_PrimaPagina.xml";
+        assert_eq!(cache_file_path(url), cache_file_path(url));
+        assert_ne!(cache_file_path(url), cache_file_path("https://example.com/other.xml"));
+    }
+
+    #[test]
+    fn test_cache_entry_round_trip() {
+        let entry = CacheEntry {
+            etag: Some("\"abc123\"".to_string()),
+            last_modified: None,
+            body: "<rss></rss>".to_string(),
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        let parsed: CacheEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.etag, entry.etag);
+        assert_eq!(parsed.body, entry.body);
+    }
+
+    #[test]
+    fn test_seen_store_round_trip() {
+        let mut store: SeenStore = HashMap::new();
+        store
+            .entry(1)
+            .or_default()
+            .insert("http://example.com/a".to_string());
+
+        let json = serde_json::to_string(&store).unwrap();
+        let parsed: SeenStore = serde_json::from_str(&json).unwrap();
+        assert!(parsed[&1].contains("http://example.com/a"));
+    }
+
+    #[test]
+    fn test_filter_items_by_seen_only_new() {
+        let seen_item = Item {
+            title: "Old".to_string(),
+            link: "http://example.com/old".to_string(),
+            description: String::new(),
+            pub_date: String::new(),
+        };
+        let new_item = Item {
+            title: "New".to_string(),
+            link: "http://example.com/new".to_string(),
+            description: String::new(),
+            pub_date: String::new(),
+        };
+        let items = vec![seen_item.clone(), new_item.clone()];
+
+        let mut seen = HashSet::new();
+        seen.insert(seen_item.link.clone());
+
+        let only_new = filter_items_by_seen(&items, &seen, true);
+        assert_eq!(only_new, vec![new_item]);
+
+        let all = filter_items_by_seen(&items, &seen, false);
+        assert_eq!(all, items);
+    }
+
+    fn sample_item() -> Item {
+        Item {
+            title: "Elezioni 2024: la giornata".to_string(),
+            link: "http://example.com/elezioni".to_string(),
+            description: "Un sondaggio sulle elezioni".to_string(),
+            pub_date: "Wed, 02 Oct 2024 13:00:00 +0200".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_query_regex_match() {
+        let predicate = parse_query(r#"title =~ "(?i)elezioni""#).unwrap();
+        assert!(predicate.eval(&sample_item()));
+    }
+
+    #[test]
+    fn test_query_and_not_contains() {
+        let predicate =
+            parse_query(r#"title =~ "(?i)elezioni" and not description contains "sondaggio""#)
+                .unwrap();
+        assert!(!predicate.eval(&sample_item()));
+    }
+
+    #[test]
+    fn test_query_or_and_parens() {
+        let predicate =
+            parse_query(r#"(link == "nope" or title contains "Elezioni") and not link == "x""#)
+                .unwrap();
+        assert!(predicate.eval(&sample_item()));
+    }
+
+    #[test]
+    fn test_query_missing_value_is_error() {
+        let err = parse_query("title ==").unwrap_err();
+        assert!(err.to_string().contains("Expected a quoted string"));
+    }
+
+    #[test]
+    fn test_parse_feed_atom() {
+        let xml = r#"<?xml version="1.0"?>
+        <feed xmlns="http://www.w3.org/2005/Atom">
+          <title>Test Feed</title>
+          <link href="http://example.com/" rel="alternate"/>
+          <entry>
+            <title>Entry 1</title>
+            <link href="http://example.com/entry1" rel="alternate"/>
+            <summary>Summary text</summary>
+            <updated>2024-10-02T13:00:00+02:00</updated>
+          </entry>
+        </feed>"#;
+        let channel = parse_feed(xml).unwrap();
+        assert_eq!(channel.title, "Test Feed");
+        assert_eq!(channel.items.len(), 1);
+        assert_eq!(channel.items[0].link, "http://example.com/entry1");
+        assert_eq!(channel.items[0].description, "Summary text");
+    }
 }